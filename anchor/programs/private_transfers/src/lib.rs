@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::poseidon::{hashv, Endianness, Parameters};
 use anchor_lang::solana_program::program::invoke;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked};
 
 declare_id!("2QRZu5cWy8x8jEFc9nhsnrnQSMAKwNpiLpCXrMRb3oUn");
 
@@ -17,31 +20,213 @@ pub const EMPTY_ROOT: [u8; 32] = [
     0x21, 0xaa, 0x3b, 0x48, 0x9d, 0x15, 0x3c, 0x06,
 ];
 
+/// Poseidon hash of an empty subtree at each level, precomputed off-chain
+/// with the same BN254 parameters the Noir circuit uses. `ZEROS[0]` is the
+/// hash of an empty leaf; `ZEROS[i]` is `hash(ZEROS[i-1], ZEROS[i-1])`.
+/// `EMPTY_ROOT` above is `ZEROS[TREE_DEPTH - 1]` hashed one level further.
+pub const ZEROS: [[u8; 32]; TREE_DEPTH] = [
+    [
+        0x2a, 0x09, 0xa9, 0xfd, 0x93, 0xc3, 0x95, 0xfc,
+        0x3c, 0x0f, 0xa5, 0x36, 0x1e, 0x2f, 0x7a, 0x0d,
+        0x5b, 0xbf, 0x0d, 0x6c, 0xd3, 0x4f, 0x4f, 0xb3,
+        0xd1, 0xcc, 0x2d, 0x80, 0x78, 0x3d, 0xb2, 0x08,
+    ],
+    [
+        0x13, 0xe3, 0x7f, 0x2d, 0x6c, 0xb8, 0x6c, 0x78,
+        0xac, 0xcd, 0x4c, 0x0f, 0x76, 0x57, 0x9e, 0x35,
+        0x6e, 0x2e, 0x0e, 0x3a, 0xb2, 0x1d, 0xae, 0x72,
+        0xb2, 0xac, 0x81, 0x38, 0x3e, 0x4b, 0x47, 0x1f,
+    ],
+    [
+        0x21, 0x0e, 0x53, 0xdf, 0x4a, 0xda, 0x7d, 0x8d,
+        0x23, 0xf2, 0x2c, 0x64, 0xb5, 0x26, 0x02, 0x00,
+        0x78, 0xbd, 0x1b, 0x88, 0xd0, 0x28, 0xb3, 0x9a,
+        0x54, 0xb6, 0xa5, 0x46, 0x74, 0x8a, 0xb4, 0x28,
+    ],
+    [
+        0x27, 0x2a, 0x55, 0x9b, 0x07, 0x76, 0xa9, 0xc9,
+        0xca, 0xde, 0x03, 0xab, 0x20, 0x76, 0x9d, 0xde,
+        0x0d, 0x4d, 0x2e, 0x0b, 0xcb, 0xc0, 0x8f, 0xa6,
+        0xd3, 0xfe, 0x0a, 0x8b, 0x8c, 0x1d, 0xd8, 0x54,
+    ],
+    [
+        0x0c, 0x00, 0x9e, 0x21, 0x9c, 0x87, 0xf0, 0x34,
+        0x0b, 0x79, 0x4f, 0xf1, 0x35, 0x95, 0x1f, 0xc6,
+        0x9b, 0x5d, 0xa9, 0x4a, 0x66, 0x99, 0x05, 0x8e,
+        0x17, 0x2b, 0x0f, 0xf2, 0xbf, 0x73, 0x4f, 0x88,
+    ],
+    [
+        0x1c, 0xf5, 0x6b, 0x1c, 0x97, 0x4e, 0x60, 0x42,
+        0x5c, 0x4e, 0x4e, 0x5b, 0x8d, 0x1f, 0x49, 0x7a,
+        0x26, 0x14, 0xb1, 0xf0, 0xab, 0x3d, 0x82, 0x5b,
+        0xb9, 0x78, 0xa6, 0xe4, 0x3a, 0x1b, 0x43, 0x92,
+    ],
+    [
+        0x17, 0x4b, 0x65, 0x4a, 0x9a, 0x0e, 0xcf, 0xd4,
+        0xd7, 0x1f, 0x33, 0x6f, 0x9c, 0x17, 0x43, 0x51,
+        0x1c, 0x9f, 0x8a, 0x9e, 0xaa, 0x85, 0x0f, 0xe1,
+        0x46, 0x55, 0x8c, 0x13, 0x93, 0xed, 0x2e, 0x2b,
+    ],
+    [
+        0x2e, 0x1f, 0x26, 0xf4, 0x3d, 0x2e, 0x0b, 0xf6,
+        0x0a, 0x83, 0x16, 0x79, 0x95, 0x45, 0x9a, 0x22,
+        0xaf, 0xb0, 0x4a, 0xf0, 0x8d, 0x8f, 0x11, 0xd4,
+        0x8f, 0xc8, 0x58, 0x32, 0x5e, 0x34, 0xb4, 0x17,
+    ],
+    [
+        0x0a, 0x89, 0xca, 0x6f, 0xfa, 0x14, 0xcc, 0x46,
+        0x2c, 0xfe, 0xdb, 0x84, 0x2c, 0x30, 0xed, 0x22,
+        0x18, 0x6e, 0xaf, 0x43, 0x38, 0x73, 0x03, 0x1b,
+        0xdd, 0x99, 0x5e, 0x20, 0x79, 0x4b, 0x66, 0x96,
+    ],
+    [
+        0x23, 0x1f, 0x5e, 0x41, 0xc5, 0xc1, 0x0a, 0xf7,
+        0xca, 0xd1, 0xad, 0xc5, 0x5f, 0xfc, 0xd6, 0x52,
+        0x9e, 0x2a, 0x89, 0x55, 0xfd, 0x74, 0x8f, 0x9b,
+        0x43, 0x79, 0x50, 0x0a, 0x7d, 0xa8, 0x3c, 0xac,
+    ],
+];
+
+/// BN254 Poseidon hash of two tree nodes, matching the Noir circuit's
+/// `poseidon2::hash([left, right])` used to build withdrawal proofs.
+fn poseidon_hash(left: &[u8; 32], right: &[u8; 32]) -> Result<[u8; 32]> {
+    let result = hashv(Parameters::Bn254X5, Endianness::BigEndian, &[left, right])
+        .map_err(|_| error!(PrivateTransfersError::PoseidonHashFailed))?;
+    Ok(result.to_bytes())
+}
+
+/// `MIN_DEPOSIT_AMOUNT` is denominated in units of 9-decimal SOL. Scale it to
+/// the deposited mint's own decimals so a pool for a low-decimal token (e.g.
+/// 6-decimal USDC) enforces an equivalent real-world minimum.
+fn scale_min_deposit(decimals: u8) -> u64 {
+    const SOL_DECIMALS: u32 = 9;
+    let decimals = decimals as u32;
+    if decimals >= SOL_DECIMALS {
+        MIN_DEPOSIT_AMOUNT.saturating_mul(10u64.saturating_pow(decimals - SOL_DECIMALS))
+    } else {
+        // Floor at 1 so a very-low-decimal mint (e.g. decimals == 0) can't
+        // divide the minimum down to 0 and silently disable the check.
+        (MIN_DEPOSIT_AMOUNT / 10u64.pow(SOL_DECIMALS - decimals)).max(1)
+    }
+}
+
+/// Resolution of a pool's external condition, set by `decide` and consumed by
+/// `withdraw`/`refund`. A pool with no `decider` configured never leaves `Pending`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum DecisionOutcome {
+    Pending,
+    Pass,
+    Fail,
+}
+
 #[program]
 pub mod private_transfers {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    /// `token_mint` is `Pubkey::default()` for a native-SOL pool, or the SPL
+    /// mint the pool accepts for a token pool; `pool.is_native` records which
+    /// one this pool is so `deposit`/`withdraw` know which transfer path to take.
+    ///
+    /// Passing `Some(decider)` turns the pool into a conditional-payment pool:
+    /// deposits stop after `mint_term_end_slot`, `decider` may call `decide`
+    /// any time up to `decide_term_end_slot`, and `withdraw` only succeeds once
+    /// the decider has resolved the outcome to `Pass`. `decider` must be
+    /// accompanied by both term slots, with `decide_term_end_slot` after
+    /// `mint_term_end_slot`.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        token_mint: Pubkey,
+        decider: Option<Pubkey>,
+        mint_term_end_slot: Option<u64>,
+        decide_term_end_slot: Option<u64>,
+    ) -> Result<()> {
+        let is_native = token_mint == Pubkey::default();
+
+        let min_deposit_amount = match &ctx.accounts.mint {
+            Some(mint) => {
+                require!(!is_native, PrivateTransfersError::UnexpectedMintAccount);
+                require_keys_eq!(mint.key(), token_mint, PrivateTransfersError::MintMismatch);
+                scale_min_deposit(mint.decimals)
+            }
+            None => {
+                require!(is_native, PrivateTransfersError::MissingMintAccount);
+                MIN_DEPOSIT_AMOUNT
+            }
+        };
+
+        if decider.is_some() {
+            let mint_term_end_slot = mint_term_end_slot.ok_or(PrivateTransfersError::InvalidTermSlots)?;
+            let decide_term_end_slot = decide_term_end_slot.ok_or(PrivateTransfersError::InvalidTermSlots)?;
+            require!(
+                decide_term_end_slot > mint_term_end_slot,
+                PrivateTransfersError::InvalidTermSlots
+            );
+        } else {
+            require!(
+                mint_term_end_slot.is_none() && decide_term_end_slot.is_none(),
+                PrivateTransfersError::InvalidTermSlots
+            );
+        }
+
         let pool = &mut ctx.accounts.pool;
         pool.authority = ctx.accounts.authority.key();
         pool.next_leaf_index = 0;
         pool.total_deposits = 0;
         pool.current_root_index = 0;
         pool.roots[0] = EMPTY_ROOT;
-
-        let nullifiers = &mut ctx.accounts.nullifier_set;
-        nullifiers.pool = pool.key();
+        pool.filled_subtrees = ZEROS;
+        pool.token_mint = token_mint;
+        pool.is_native = is_native;
+        pool.min_deposit_amount = min_deposit_amount;
+        pool.decider = decider;
+        pool.mint_term_end_slot = mint_term_end_slot;
+        pool.decide_term_end_slot = decide_term_end_slot;
+        pool.outcome = DecisionOutcome::Pending;
 
         msg!("Pool initialized");
         Ok(())
     }
 
-    /// Client computes commitment and new_root off-chain.
-    /// Invalid roots will cause withdrawal proofs to fail.
+    /// Resolves the pool's external condition. Only callable by `pool.decider`
+    /// and only before `decide_term_end_slot`; the outcome then gates `withdraw`.
+    pub fn decide(ctx: Context<Decide>, outcome: bool) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        let decide_term_end_slot = pool
+            .decide_term_end_slot
+            .ok_or(PrivateTransfersError::NoDeciderConfigured)?;
+
+        require!(
+            Clock::get()?.slot <= decide_term_end_slot,
+            PrivateTransfersError::DecideWindowClosed
+        );
+
+        require!(
+            pool.outcome == DecisionOutcome::Pending,
+            PrivateTransfersError::AlreadyDecided
+        );
+
+        pool.outcome = if outcome {
+            DecisionOutcome::Pass
+        } else {
+            DecisionOutcome::Fail
+        };
+
+        emit!(DecideEvent {
+            outcome: pool.outcome,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Pool decided: {}", outcome);
+        Ok(())
+    }
+
+    /// Client computes the commitment off-chain; the new Merkle root is
+    /// derived on-chain from the incremental tree so a depositor cannot
+    /// forge a root that a withdrawal proof would otherwise trust.
     pub fn deposit(
         ctx: Context<Deposit>,
         commitment: [u8; 32],
-        new_root: [u8; 32],
         amount: u64,
     ) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
@@ -52,26 +237,69 @@ pub mod private_transfers {
         );
 
         require!(
-            amount >= MIN_DEPOSIT_AMOUNT,
+            amount >= pool.min_deposit_amount,
             PrivateTransfersError::DepositTooSmall
         );
 
-        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.depositor.key(),
-            &ctx.accounts.pool_vault.key(),
-            amount,
-        );
+        if let Some(mint_term_end_slot) = pool.mint_term_end_slot {
+            require!(
+                Clock::get()?.slot <= mint_term_end_slot,
+                PrivateTransfersError::MintWindowClosed
+            );
+        }
 
-        invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.depositor.to_account_info(),
-                ctx.accounts.pool_vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
+        if pool.is_native {
+            let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.depositor.key(),
+                &ctx.accounts.pool_vault.key(),
+                amount,
+            );
+
+            invoke(
+                &transfer_ix,
+                &[
+                    ctx.accounts.depositor.to_account_info(),
+                    ctx.accounts.pool_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        } else {
+            let mint = ctx.accounts.mint.as_ref().ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            require_keys_eq!(mint.key(), pool.token_mint, PrivateTransfersError::MintMismatch);
+            let depositor_token_account = ctx
+                .accounts
+                .depositor_token_account
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let pool_token_vault = ctx
+                .accounts
+                .pool_token_vault
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+
+            token::transfer_checked(
+                CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: depositor_token_account.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: pool_token_vault.to_account_info(),
+                        authority: ctx.accounts.depositor.to_account_info(),
+                    },
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
 
         let leaf_index = pool.next_leaf_index;
+        let new_root = pool.insert_leaf(commitment)?;
+
         let new_root_index = ((pool.current_root_index + 1) % ROOT_HISTORY_SIZE as u64) as usize;
         pool.current_root_index = new_root_index as u64;
         pool.roots[new_root_index] = new_root;
@@ -90,6 +318,13 @@ pub mod private_transfers {
         Ok(())
     }
 
+    /// A relayer with no stake in the withdrawal can submit this transaction
+    /// on behalf of `recipient`, paying the transaction fee and the
+    /// `nullifier_record` rent, and be reimbursed `fee` lamports/tokens out
+    /// of the withdrawn `amount`. `relayer` and `fee` are bound into the
+    /// proof's public inputs so a relayer can't redirect or inflate its cut
+    /// without invalidating the proof, and `recipient` never needs its own
+    /// SOL to receive a private payout.
     pub fn withdraw(
         ctx: Context<Withdraw>,
         proof: Vec<u8>,
@@ -97,14 +332,13 @@ pub mod private_transfers {
         root: [u8; 32],
         recipient: Pubkey,
         amount: u64,
+        relayer: Pubkey,
+        fee: u64,
     ) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
-        let nullifier_set = &mut ctx.accounts.nullifier_set;
 
-        require!(
-            !nullifier_set.is_nullifier_used(&nullifier_hash),
-            PrivateTransfersError::NullifierUsed
-        );
+        // Anchor's `init` on `nullifier_record` above already rejects a
+        // replayed `nullifier_hash`; no separate used-set lookup is needed.
 
         require!(
             pool.is_known_root(&root),
@@ -117,13 +351,48 @@ pub mod private_transfers {
             PrivateTransfersError::RecipientMismatch
         );
 
+        // Prevents a third party from resubmitting the same proof with
+        // themselves as the relayer and collecting `fee`
         require!(
-            ctx.accounts.pool_vault.lamports() >= amount,
-            PrivateTransfersError::InsufficientVaultBalance
+            ctx.accounts.relayer.key() == relayer,
+            PrivateTransfersError::RelayerMismatch
         );
 
+        require!(fee < amount, PrivateTransfersError::RelayerFeeTooHigh);
+
+        if let Some(decide_term_end_slot) = pool.decide_term_end_slot {
+            require!(
+                Clock::get()?.slot > decide_term_end_slot,
+                PrivateTransfersError::DecideWindowNotOver
+            );
+            require!(
+                pool.outcome == DecisionOutcome::Pass,
+                PrivateTransfersError::WithdrawNotAllowed
+            );
+        }
+
+        if pool.is_native {
+            require!(
+                ctx.accounts.pool_vault.lamports() >= amount,
+                PrivateTransfersError::InsufficientVaultBalance
+            );
+        } else {
+            let mint = ctx.accounts.mint.as_ref().ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            require_keys_eq!(mint.key(), pool.token_mint, PrivateTransfersError::MintMismatch);
+            let pool_token_vault = ctx
+                .accounts
+                .pool_token_vault
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            require!(
+                pool_token_vault.amount >= amount,
+                PrivateTransfersError::InsufficientVaultBalance
+            );
+        }
+
         // Verify ZK proof via CPI to Sunspot
-        let public_inputs = encode_public_inputs(&root, &nullifier_hash, &recipient, amount);
+        let public_inputs =
+            encode_withdraw_public_inputs(&root, &nullifier_hash, &recipient, amount, &relayer, fee);
         let instruction_data = [proof.as_slice(), public_inputs.as_slice()].concat();
 
         invoke(
@@ -135,32 +404,247 @@ pub mod private_transfers {
             &[ctx.accounts.verifier_program.to_account_info()],
         )?;
 
-        nullifier_set.mark_nullifier_used(nullifier_hash)?;
-
         let pool_key = pool.key();
-        let seeds = &[b"vault".as_ref(), pool_key.as_ref(), &[ctx.bumps.pool_vault]];
+        let payout = amount - fee;
 
-        anchor_lang::solana_program::program::invoke_signed(
-            &anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.pool_vault.key(),
-                &ctx.accounts.recipient.key(),
-                amount,
-            ),
-            &[
-                ctx.accounts.pool_vault.to_account_info(),
-                ctx.accounts.recipient.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[seeds],
-        )?;
+        if pool.is_native {
+            let vault_seeds = &[b"vault".as_ref(), pool_key.as_ref(), &[ctx.bumps.pool_vault]];
+
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.pool_vault.key(),
+                    &ctx.accounts.recipient.key(),
+                    payout,
+                ),
+                &[
+                    ctx.accounts.pool_vault.to_account_info(),
+                    ctx.accounts.recipient.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[vault_seeds],
+            )?;
+
+            if fee > 0 {
+                anchor_lang::solana_program::program::invoke_signed(
+                    &anchor_lang::solana_program::system_instruction::transfer(
+                        &ctx.accounts.pool_vault.key(),
+                        &ctx.accounts.relayer.key(),
+                        fee,
+                    ),
+                    &[
+                        ctx.accounts.pool_vault.to_account_info(),
+                        ctx.accounts.relayer.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                    &[vault_seeds],
+                )?;
+            }
+        } else {
+            let mint = ctx.accounts.mint.as_ref().ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let recipient_token_account = ctx
+                .accounts
+                .recipient_token_account
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let pool_token_vault = ctx
+                .accounts
+                .pool_token_vault
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+
+            let pool_seeds = &[b"pool".as_ref(), &[ctx.bumps.pool]];
+
+            token::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: pool_token_vault.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: recipient_token_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[pool_seeds],
+                ),
+                payout,
+                mint.decimals,
+            )?;
+
+            if fee > 0 {
+                let relayer_token_account = ctx
+                    .accounts
+                    .relayer_token_account
+                    .as_ref()
+                    .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+
+                token::transfer_checked(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        TransferChecked {
+                            from: pool_token_vault.to_account_info(),
+                            mint: mint.to_account_info(),
+                            to: relayer_token_account.to_account_info(),
+                            authority: pool.to_account_info(),
+                        },
+                        &[pool_seeds],
+                    ),
+                    fee,
+                    mint.decimals,
+                )?;
+            }
+        }
 
         emit!(WithdrawEvent {
             nullifier_hash,
+            nullifier_record: ctx.accounts.nullifier_record.key(),
             recipient: ctx.accounts.recipient.key(),
+            relayer: ctx.accounts.relayer.key(),
+            fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Withdrawal: {} lamports to {} ({} lamports fee to relayer)", payout, recipient, fee);
+        Ok(())
+    }
+
+    /// Returns a conditional-pool deposit to its original depositor when the
+    /// outcome resolved to `Fail`, or never resolved before `decide_term_end_slot`.
+    pub fn refund(
+        ctx: Context<Refund>,
+        proof: Vec<u8>,
+        nullifier_hash: [u8; 32],
+        root: [u8; 32],
+        depositor: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+
+        let decide_term_end_slot = pool
+            .decide_term_end_slot
+            .ok_or(PrivateTransfersError::NoDeciderConfigured)?;
+
+        require!(
+            Clock::get()?.slot > decide_term_end_slot,
+            PrivateTransfersError::DecideWindowNotOver
+        );
+
+        require!(
+            pool.outcome != DecisionOutcome::Pass,
+            PrivateTransfersError::RefundNotAllowed
+        );
+
+        // Anchor's `init` on `nullifier_record` above already rejects a
+        // replayed `nullifier_hash`; no separate used-set lookup is needed.
+
+        require!(
+            pool.is_known_root(&root),
+            PrivateTransfersError::InvalidRoot
+        );
+
+        // Prevents front-running by binding proof to the original depositor
+        require!(
+            ctx.accounts.depositor.key() == depositor,
+            PrivateTransfersError::RecipientMismatch
+        );
+
+        if pool.is_native {
+            require!(
+                ctx.accounts.pool_vault.lamports() >= amount,
+                PrivateTransfersError::InsufficientVaultBalance
+            );
+        } else {
+            let mint = ctx.accounts.mint.as_ref().ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            require_keys_eq!(mint.key(), pool.token_mint, PrivateTransfersError::MintMismatch);
+            let pool_token_vault = ctx
+                .accounts
+                .pool_token_vault
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            require!(
+                pool_token_vault.amount >= amount,
+                PrivateTransfersError::InsufficientVaultBalance
+            );
+        }
+
+        // Verify ZK proof via CPI to Sunspot
+        let public_inputs = encode_public_inputs(&root, &nullifier_hash, &depositor, amount);
+        let instruction_data = [proof.as_slice(), public_inputs.as_slice()].concat();
+
+        invoke(
+            &Instruction {
+                program_id: ctx.accounts.verifier_program.key(),
+                accounts: vec![],
+                data: instruction_data,
+            },
+            &[ctx.accounts.verifier_program.to_account_info()],
+        )?;
+
+        let pool_key = pool.key();
+
+        if pool.is_native {
+            let vault_seeds = &[b"vault".as_ref(), pool_key.as_ref(), &[ctx.bumps.pool_vault]];
+
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.pool_vault.key(),
+                    &ctx.accounts.depositor.key(),
+                    amount,
+                ),
+                &[
+                    ctx.accounts.pool_vault.to_account_info(),
+                    ctx.accounts.depositor.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[vault_seeds],
+            )?;
+        } else {
+            let mint = ctx.accounts.mint.as_ref().ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let depositor_token_account = ctx
+                .accounts
+                .depositor_token_account
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let pool_token_vault = ctx
+                .accounts
+                .pool_token_vault
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+            let token_program = ctx
+                .accounts
+                .token_program
+                .as_ref()
+                .ok_or(PrivateTransfersError::TokenAccountsRequired)?;
+
+            let pool_seeds = &[b"pool".as_ref(), &[ctx.bumps.pool]];
+
+            token::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: pool_token_vault.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: depositor_token_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[pool_seeds],
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
+
+        emit!(RefundEvent {
+            nullifier_hash,
+            nullifier_record: ctx.accounts.nullifier_record.key(),
+            depositor: ctx.accounts.depositor.key(),
             timestamp: Clock::get()?.unix_timestamp,
         });
 
-        msg!("Withdrawal: {} lamports to {}", amount, recipient);
+        msg!("Refund: {} lamports to {}", amount, depositor);
         Ok(())
     }
 }
@@ -191,6 +675,42 @@ fn encode_public_inputs(
     inputs
 }
 
+/// Gnark witness format for `withdraw`: 12-byte header + 6x32-byte public
+/// inputs. Binds `relayer` and `fee` alongside the original four inputs so a
+/// relayer can't tamper with its own cut without invalidating the proof.
+fn encode_withdraw_public_inputs(
+    root: &[u8; 32],
+    nullifier_hash: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    relayer: &Pubkey,
+    fee: u64,
+) -> Vec<u8> {
+    const NR_PUBLIC_INPUTS: u32 = 6;
+    let mut inputs = Vec::with_capacity(12 + 192);
+
+    // Header: num_public (4) | num_private (4) | vector_len (4)
+    inputs.extend_from_slice(&NR_PUBLIC_INPUTS.to_be_bytes());
+    inputs.extend_from_slice(&0u32.to_be_bytes());
+    inputs.extend_from_slice(&NR_PUBLIC_INPUTS.to_be_bytes());
+
+    inputs.extend_from_slice(root);
+    inputs.extend_from_slice(nullifier_hash);
+    inputs.extend_from_slice(recipient.as_ref());
+
+    let mut amount_bytes = [0u8; 32];
+    amount_bytes[24..32].copy_from_slice(&amount.to_be_bytes());
+    inputs.extend_from_slice(&amount_bytes);
+
+    inputs.extend_from_slice(relayer.as_ref());
+
+    let mut fee_bytes = [0u8; 32];
+    fee_bytes[24..32].copy_from_slice(&fee.to_be_bytes());
+    inputs.extend_from_slice(&fee_bytes);
+
+    inputs
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -202,22 +722,27 @@ pub struct Initialize<'info> {
     )]
     pub pool: Account<'info, Pool>,
 
+    /// CHECK: PDA validated by seeds; holds lamports directly for native-SOL pools
+    #[account(seeds = [b"vault", pool.key().as_ref()], bump)]
+    pub pool_vault: UncheckedAccount<'info>,
+
+    /// Mint of the deposited token; omitted (pass the program ID) for native-SOL pools
+    pub mint: Option<Account<'info, Mint>>,
+
+    /// Associated token account owned by the pool PDA; only created for SPL-token pools
     #[account(
-        init,
+        init_if_needed,
         payer = authority,
-        space = 8 + NullifierSet::INIT_SPACE,
-        seeds = [b"nullifiers", pool.key().as_ref()],
-        bump
+        associated_token::mint = mint,
+        associated_token::authority = pool,
     )]
-    pub nullifier_set: Account<'info, NullifierSet>,
-
-    /// CHECK: PDA validated by seeds
-    #[account(seeds = [b"vault", pool.key().as_ref()], bump)]
-    pub pool_vault: UncheckedAccount<'info>,
+    pub pool_token_vault: Option<Account<'info, TokenAccount>>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
 }
 
 #[derive(Accounts)]
@@ -225,36 +750,174 @@ pub struct Deposit<'info> {
     #[account(mut, seeds = [b"pool"], bump)]
     pub pool: Account<'info, Pool>,
 
-    /// CHECK: PDA validated by seeds
+    /// CHECK: PDA validated by seeds; used only for native-SOL pools
     #[account(mut, seeds = [b"vault", pool.key().as_ref()], bump)]
     pub pool_vault: UncheckedAccount<'info>,
 
+    /// Omitted (pass the program ID) for native-SOL pools
+    pub mint: Option<Account<'info, Mint>>,
+
+    /// Pinned to the pool's own ATA so a depositor can't substitute a
+    /// throwaway account and have their commitment credited for free.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+    )]
+    pub pool_token_vault: Option<Account<'info, TokenAccount>>,
+
     #[account(mut)]
     pub depositor: Signer<'info>,
+
+    /// Pinned to `depositor`'s own ATA, matching the same pattern as every
+    /// other token account in the program.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = depositor,
+    )]
+    pub depositor_token_account: Option<Account<'info, TokenAccount>>,
+
     pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
 }
 
 #[derive(Accounts)]
+#[instruction(proof: Vec<u8>, nullifier_hash: [u8; 32], root: [u8; 32], recipient: Pubkey, amount: u64, relayer: Pubkey, fee: u64)]
 pub struct Withdraw<'info> {
     #[account(mut, seeds = [b"pool"], bump)]
     pub pool: Account<'info, Pool>,
 
-    #[account(mut, seeds = [b"nullifiers", pool.key().as_ref()], bump)]
-    pub nullifier_set: Account<'info, NullifierSet>,
+    /// One PDA per nullifier: `init` fails if this nullifier was already
+    /// spent, giving O(1) double-spend detection with no capacity limit.
+    /// Paid for by `relayer` rather than `recipient`, since `recipient`
+    /// stays unfunded under gasless, relayer-submitted withdrawals.
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [b"nullifier", pool.key().as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
 
-    /// CHECK: PDA validated by seeds
+    /// CHECK: PDA validated by seeds; used only for native-SOL pools
     #[account(mut, seeds = [b"vault", pool.key().as_ref()], bump)]
     pub pool_vault: UncheckedAccount<'info>,
 
-    /// CHECK: Validated in instruction logic
+    /// Omitted (pass the program ID) for native-SOL pools
+    pub mint: Option<Account<'info, Mint>>,
+
+    /// Pinned to the pool's own ATA so a withdrawal can't be paid out of an
+    /// attacker-controlled token account.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+    )]
+    pub pool_token_vault: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: validated against the `recipient` instruction argument; never
+    /// required to sign, so a fresh, unfunded address can receive a payout
     #[account(mut)]
     pub recipient: UncheckedAccount<'info>,
 
+    /// Pinned to `recipient`'s own ATA so the relayer, who assembles this
+    /// transaction while `recipient` never signs, can't redirect the payout
+    /// to a token account they control instead.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = recipient,
+    )]
+    pub recipient_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Submits the transaction and fronts its fees; reimbursed `fee` out of
+    /// the withdrawn `amount`
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    /// Pinned to the signing relayer's own ATA so the SPL fee payout can't
+    /// be redirected to an account the relayer doesn't control.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = relayer,
+    )]
+    pub relayer_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Validated by constraint
+    #[account(constraint = verifier_program.key() == SUNSPOT_VERIFIER_ID @ PrivateTransfersError::InvalidVerifier)]
+    pub verifier_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct Decide<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool"],
+        bump,
+        constraint = pool.decider == Some(decider.key()) @ PrivateTransfersError::DeciderMismatch
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub decider: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proof: Vec<u8>, nullifier_hash: [u8; 32], root: [u8; 32], depositor: Pubkey, amount: u64)]
+pub struct Refund<'info> {
+    #[account(mut, seeds = [b"pool"], bump)]
+    pub pool: Account<'info, Pool>,
+
+    /// One PDA per nullifier: `init` fails if this nullifier was already
+    /// spent, giving O(1) double-spend detection with no capacity limit.
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + NullifierRecord::INIT_SPACE,
+        seeds = [b"nullifier", pool.key().as_ref(), nullifier_hash.as_ref()],
+        bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+
+    /// CHECK: PDA validated by seeds; used only for native-SOL pools
+    #[account(mut, seeds = [b"vault", pool.key().as_ref()], bump)]
+    pub pool_vault: UncheckedAccount<'info>,
+
+    /// Omitted (pass the program ID) for native-SOL pools
+    pub mint: Option<Account<'info, Mint>>,
+
+    /// Pinned to the pool's own ATA so a refund can't be paid out of an
+    /// attacker-controlled token account.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = pool,
+    )]
+    pub pool_token_vault: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// Pinned to `depositor`'s own ATA so a refund is always returned to the
+    /// original depositor's own token account.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = depositor,
+    )]
+    pub depositor_token_account: Option<Account<'info, TokenAccount>>,
+
     /// CHECK: Validated by constraint
     #[account(constraint = verifier_program.key() == SUNSPOT_VERIFIER_ID @ PrivateTransfersError::InvalidVerifier)]
     pub verifier_program: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
+    pub token_program: Option<Program<'info, Token>>,
 }
 
 #[account]
@@ -265,37 +928,51 @@ pub struct Pool {
     pub total_deposits: u64,
     pub current_root_index: u64,
     pub roots: [[u8; 32]; ROOT_HISTORY_SIZE],
+    pub filled_subtrees: [[u8; 32]; TREE_DEPTH],
+    pub token_mint: Pubkey,
+    pub is_native: bool,
+    pub min_deposit_amount: u64,
+    pub decider: Option<Pubkey>,
+    pub mint_term_end_slot: Option<u64>,
+    pub decide_term_end_slot: Option<u64>,
+    pub outcome: DecisionOutcome,
 }
 
 impl Pool {
     pub fn is_known_root(&self, root: &[u8; 32]) -> bool {
         self.roots.iter().any(|r| r == root)
     }
-}
 
-#[account]
-#[derive(InitSpace)]
-pub struct NullifierSet {
-    pub pool: Pubkey,
-    #[max_len(256)]
-    pub nullifiers: Vec<[u8; 32]>,
-}
+    /// Inserts `leaf` at `next_leaf_index` into the incremental Merkle tree
+    /// and returns the resulting root, updating `filled_subtrees` along the
+    /// way so the next insertion can reuse the already-hashed siblings.
+    pub fn insert_leaf(&mut self, leaf: [u8; 32]) -> Result<[u8; 32]> {
+        let mut cur = leaf;
+        let mut idx = self.next_leaf_index;
 
-impl NullifierSet {
-    pub fn is_nullifier_used(&self, nullifier_hash: &[u8; 32]) -> bool {
-        self.nullifiers.contains(nullifier_hash)
-    }
+        for level in 0..TREE_DEPTH {
+            let (left, right) = if idx % 2 == 0 {
+                self.filled_subtrees[level] = cur;
+                (cur, ZEROS[level])
+            } else {
+                (self.filled_subtrees[level], cur)
+            };
 
-    pub fn mark_nullifier_used(&mut self, nullifier_hash: [u8; 32]) -> Result<()> {
-        require!(
-            self.nullifiers.len() < 256,
-            PrivateTransfersError::NullifierSetFull
-        );
-        self.nullifiers.push(nullifier_hash);
-        Ok(())
+            cur = poseidon_hash(&left, &right)?;
+            idx >>= 1;
+        }
+
+        Ok(cur)
     }
 }
 
+/// Marker account whose mere existence at the PDA derived from
+/// `[b"nullifier", pool, nullifier_hash]` means that nullifier has been spent;
+/// `init` on this account is what rejects a replay, so it carries no fields.
+#[account]
+#[derive(InitSpace)]
+pub struct NullifierRecord {}
+
 #[event]
 pub struct DepositEvent {
     pub commitment: [u8; 32],
@@ -307,7 +984,24 @@ pub struct DepositEvent {
 #[event]
 pub struct WithdrawEvent {
     pub nullifier_hash: [u8; 32],
+    pub nullifier_record: Pubkey,
     pub recipient: Pubkey,
+    pub relayer: Pubkey,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DecideEvent {
+    pub outcome: DecisionOutcome,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundEvent {
+    pub nullifier_hash: [u8; 32],
+    pub nullifier_record: Pubkey,
+    pub depositor: Pubkey,
     pub timestamp: i64,
 }
 
@@ -317,16 +1011,44 @@ pub enum PrivateTransfersError {
     TreeFull,
     #[msg("Invalid Merkle root")]
     InvalidRoot,
-    #[msg("Nullifier already used")]
-    NullifierUsed,
     #[msg("Deposit amount too small (minimum 0.001 SOL)")]
     DepositTooSmall,
-    #[msg("Nullifier set is full")]
-    NullifierSetFull,
     #[msg("Recipient account does not match recipient parameter")]
     RecipientMismatch,
     #[msg("Invalid verifier program")]
     InvalidVerifier,
     #[msg("Insufficient vault balance for withdrawal")]
     InsufficientVaultBalance,
+    #[msg("Poseidon hash syscall failed")]
+    PoseidonHashFailed,
+    #[msg("Mint account does not match pool.token_mint")]
+    MintMismatch,
+    #[msg("Mint account provided for a native-SOL pool")]
+    UnexpectedMintAccount,
+    #[msg("Mint account required to initialize an SPL-token pool")]
+    MissingMintAccount,
+    #[msg("Token accounts required for an SPL-token pool")]
+    TokenAccountsRequired,
+    #[msg("Deposit window has closed")]
+    MintWindowClosed,
+    #[msg("decide_term_end_slot and mint_term_end_slot are inconsistent with the configured decider")]
+    InvalidTermSlots,
+    #[msg("Pool has no decider configured")]
+    NoDeciderConfigured,
+    #[msg("Decide window has closed")]
+    DecideWindowClosed,
+    #[msg("Pool outcome has already been decided")]
+    AlreadyDecided,
+    #[msg("Decide window has not yet closed")]
+    DecideWindowNotOver,
+    #[msg("Withdrawal requires a decided outcome of Pass")]
+    WithdrawNotAllowed,
+    #[msg("Refund is not allowed once the outcome has passed")]
+    RefundNotAllowed,
+    #[msg("Signer does not match pool.decider")]
+    DeciderMismatch,
+    #[msg("Relayer fee must be less than the withdrawal amount")]
+    RelayerFeeTooHigh,
+    #[msg("Relayer account does not match relayer parameter")]
+    RelayerMismatch,
 }
@@ -5,12 +5,20 @@
 /// are in tests/e2e.ts (requires backend server for proof generation).
 
 use anchor_litesvm::AnchorLiteSVM;
+use anchor_lang::AccountDeserialize;
+use anchor_lang::solana_program::poseidon::{hashv, Endianness, Parameters};
 use solana_sdk::{
+    account::Account,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
 };
 use sha2::{Sha256, Digest};
 
+// The program's own crate, available to these integration tests under its
+// real name (the IDL-generated client below is aliased to `pt_program` to
+// avoid colliding with it).
+use private_transfers::{Pool, EMPTY_ROOT, TREE_DEPTH, ZEROS};
+
 // System program ID
 const SYSTEM_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("11111111111111111111111111111111");
 
@@ -41,14 +49,6 @@ fn compute_commitment(nullifier: &[u8; 32], secret: &[u8; 32], amount: u64) -> [
     hasher.finalize().into()
 }
 
-/// Compute a test Merkle root using SHA256 (placeholder for Poseidon)
-fn compute_new_root(commitment: &[u8; 32], leaf_index: u64) -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(commitment);
-    hasher.update(&leaf_index.to_le_bytes());
-    hasher.finalize().into()
-}
-
 /// Generate random 32 bytes
 fn random_bytes() -> [u8; 32] {
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -72,9 +72,60 @@ fn find_vault_pda(pool: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"vault", pool.as_ref()], &PROGRAM_ID)
 }
 
-/// Find PDA for nullifier set
-fn find_nullifier_set_pda(pool: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[b"nullifiers", pool.as_ref()], &PROGRAM_ID)
+// Verifier program ID must match lib.rs's SUNSPOT_VERIFIER_ID
+const SUNSPOT_VERIFIER_ID: Pubkey = solana_sdk::pubkey!("CU2Vgym4wiTNcJCuW6r7DV6bCGULJxKdwFjfGfmksSVZ");
+
+/// Same BN254 Poseidon hash `Pool::insert_leaf` uses on-chain, called here
+/// independently of that function so a test comparing against it actually
+/// means something.
+fn poseidon_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(Parameters::Bn254X5, Endianness::BigEndian, &[left, right])
+        .expect("poseidon hash")
+        .to_bytes()
+}
+
+/// Clears the top three bits of a SHA256 test commitment so it lies below the
+/// BN254 field modulus; the Poseidon syscall rejects any input at or above it.
+fn to_field_element(mut bytes: [u8; 32]) -> [u8; 32] {
+    bytes[0] &= 0x1f;
+    bytes
+}
+
+/// Reimplements the incremental-tree insertion independently of
+/// `Pool::insert_leaf`: builds up `filled_subtrees` and folds in each leaf
+/// the same way, but as free-standing test code rather than calling the
+/// on-chain function, so a swapped left/right or an off-by-one in the
+/// parity check on either side would make the two disagree.
+fn expected_root_after_inserts(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut filled_subtrees = ZEROS;
+    let mut root = EMPTY_ROOT;
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        let mut cur = *leaf;
+        let mut idx = i as u64;
+
+        for level in 0..TREE_DEPTH {
+            let (left, right) = if idx % 2 == 0 {
+                filled_subtrees[level] = cur;
+                (cur, ZEROS[level])
+            } else {
+                (filled_subtrees[level], cur)
+            };
+
+            cur = poseidon_hash(&left, &right);
+            idx >>= 1;
+        }
+
+        root = cur;
+    }
+
+    root
+}
+
+/// Fetches and deserializes the on-chain `Pool` account.
+fn fetch_pool(ctx: &AnchorLiteSVM, pool_pda: &Pubkey) -> Pool {
+    let account = ctx.svm.get_account(pool_pda).expect("pool account should exist");
+    Pool::try_deserialize(&mut account.data.as_slice()).expect("pool should deserialize")
 }
 
 #[test]
@@ -92,18 +143,25 @@ fn test_initialize_pool() {
     // Find PDAs
     let (pool_pda, _) = find_pool_pda();
     let (vault_pda, _) = find_vault_pda(&pool_pda);
-    let (nullifier_set_pda, _) = find_nullifier_set_pda(&pool_pda);
 
     // Build initialize instruction using anchor-litesvm
     let accounts = client::accounts::Initialize {
         pool: pool_pda,
-        nullifier_set: nullifier_set_pda,
         pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
         authority: authority.pubkey(),
         system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
     };
 
-    let args = client::args::Initialize {};
+    let args = client::args::Initialize {
+        token_mint: Pubkey::default(),
+        decider: None,
+        mint_term_end_slot: None,
+        decide_term_end_slot: None,
+    };
 
     let ix = ctx.program()
         .accounts(accounts)
@@ -137,20 +195,27 @@ fn test_single_deposit() {
     // Find PDAs
     let (pool_pda, _) = find_pool_pda();
     let (vault_pda, _) = find_vault_pda(&pool_pda);
-    let (nullifier_set_pda, _) = find_nullifier_set_pda(&pool_pda);
 
     // Initialize pool first
     let init_accounts = client::accounts::Initialize {
         pool: pool_pda,
-        nullifier_set: nullifier_set_pda,
         pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
         authority: authority.pubkey(),
         system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
     };
 
     let init_ix = ctx.program()
         .accounts(init_accounts)
-        .args(client::args::Initialize {})
+        .args(client::args::Initialize {
+        token_mint: Pubkey::default(),
+        decider: None,
+        mint_term_end_slot: None,
+        decide_term_end_slot: None,
+    })
         .instruction()
         .unwrap();
 
@@ -161,18 +226,21 @@ fn test_single_deposit() {
     let nullifier = random_bytes();
     let secret = random_bytes();
     let commitment = compute_commitment(&nullifier, &secret, deposit_amount);
-    let new_root = compute_new_root(&commitment, 0);
+    let commitment = to_field_element(commitment);
 
     let deposit_accounts = client::accounts::Deposit {
         pool: pool_pda,
         pool_vault: vault_pda,
+        mint: None,
+        depositor_token_account: None,
+        pool_token_vault: None,
         depositor: authority.pubkey(),
         system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
     };
 
     let deposit_args = client::args::Deposit {
         commitment,
-        new_root,
         amount: deposit_amount,
     };
 
@@ -203,20 +271,27 @@ fn test_multiple_deposits() {
     // Find PDAs
     let (pool_pda, _) = find_pool_pda();
     let (vault_pda, _) = find_vault_pda(&pool_pda);
-    let (nullifier_set_pda, _) = find_nullifier_set_pda(&pool_pda);
 
     // Initialize pool first
     let init_accounts = client::accounts::Initialize {
         pool: pool_pda,
-        nullifier_set: nullifier_set_pda,
         pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
         authority: authority.pubkey(),
         system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
     };
 
     let init_ix = ctx.program()
         .accounts(init_accounts)
-        .args(client::args::Initialize {})
+        .args(client::args::Initialize {
+        token_mint: Pubkey::default(),
+        decider: None,
+        mint_term_end_slot: None,
+        decide_term_end_slot: None,
+    })
         .instruction()
         .unwrap();
 
@@ -228,18 +303,21 @@ fn test_multiple_deposits() {
         let nullifier = random_bytes();
         let secret = random_bytes();
         let commitment = compute_commitment(&nullifier, &secret, deposit_amount);
-        let new_root = compute_new_root(&commitment, i as u64);
+        let commitment = to_field_element(commitment);
 
         let deposit_accounts = client::accounts::Deposit {
             pool: pool_pda,
             pool_vault: vault_pda,
+            mint: None,
+            depositor_token_account: None,
+            pool_token_vault: None,
             depositor: authority.pubkey(),
             system_program: SYSTEM_PROGRAM_ID,
+            token_program: None,
         };
 
         let deposit_args = client::args::Deposit {
             commitment,
-            new_root,
             amount: deposit_amount,
         };
 
@@ -273,20 +351,27 @@ fn test_reject_small_deposit() {
     // Find PDAs
     let (pool_pda, _) = find_pool_pda();
     let (vault_pda, _) = find_vault_pda(&pool_pda);
-    let (nullifier_set_pda, _) = find_nullifier_set_pda(&pool_pda);
 
     // Initialize pool first
     let init_accounts = client::accounts::Initialize {
         pool: pool_pda,
-        nullifier_set: nullifier_set_pda,
         pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
         authority: authority.pubkey(),
         system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
     };
 
     let init_ix = ctx.program()
         .accounts(init_accounts)
-        .args(client::args::Initialize {})
+        .args(client::args::Initialize {
+        token_mint: Pubkey::default(),
+        decider: None,
+        mint_term_end_slot: None,
+        decide_term_end_slot: None,
+    })
         .instruction()
         .unwrap();
 
@@ -297,18 +382,20 @@ fn test_reject_small_deposit() {
     let nullifier = random_bytes();
     let secret = random_bytes();
     let commitment = compute_commitment(&nullifier, &secret, below_min_amount);
-    let new_root = compute_new_root(&commitment, 0);
 
     let deposit_accounts = client::accounts::Deposit {
         pool: pool_pda,
         pool_vault: vault_pda,
+        mint: None,
+        depositor_token_account: None,
+        pool_token_vault: None,
         depositor: authority.pubkey(),
         system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
     };
 
     let deposit_args = client::args::Deposit {
         commitment,
-        new_root,
         amount: below_min_amount,
     };
 
@@ -326,3 +413,699 @@ fn test_reject_small_deposit() {
 
     println!("✓ Small deposit correctly rejected");
 }
+
+#[test]
+fn test_decide_rejects_wrong_signer() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let decider = Keypair::new();
+    let impostor = Keypair::new();
+    ctx.svm.airdrop(&impostor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: Some(decider.pubkey()),
+            mint_term_end_slot: Some(10),
+            decide_term_end_slot: Some(20),
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    // Impostor, not the configured decider, tries to resolve the outcome
+    let decide_accounts = client::accounts::Decide {
+        pool: pool_pda,
+        decider: impostor.pubkey(),
+    };
+
+    let decide_ix = ctx.program()
+        .accounts(decide_accounts)
+        .args(client::args::Decide { outcome: true })
+        .instruction()
+        .unwrap();
+
+    let result = ctx.execute_instruction(decide_ix, &[&impostor]).unwrap();
+
+    // This should fail with DeciderMismatch error
+    result.assert_failure();
+
+    println!("✓ Decide correctly rejects a signer that isn't pool.decider");
+}
+
+#[test]
+fn test_refund_rejected_before_decide_window_closes() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let decider = Keypair::new();
+    let depositor = Keypair::new();
+    ctx.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    // decide_term_end_slot is far in the future; the window has not closed yet
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: Some(decider.pubkey()),
+            mint_term_end_slot: Some(1_000),
+            decide_term_end_slot: Some(1_000_000),
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    let nullifier_hash = random_bytes();
+    let (nullifier_pda, _) =
+        Pubkey::find_program_address(&[b"nullifier", pool_pda.as_ref(), &nullifier_hash], &PROGRAM_ID);
+
+    let refund_accounts = client::accounts::Refund {
+        pool: pool_pda,
+        nullifier_record: nullifier_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        depositor_token_account: None,
+        pool_token_vault: None,
+        depositor: depositor.pubkey(),
+        verifier_program: SUNSPOT_VERIFIER_ID,
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+    };
+
+    let refund_args = client::args::Refund {
+        proof: vec![],
+        nullifier_hash,
+        root: [0u8; 32],
+        depositor: depositor.pubkey(),
+        amount: LAMPORTS_PER_SOL / 10,
+    };
+
+    let refund_ix = ctx.program()
+        .accounts(refund_accounts)
+        .args(refund_args)
+        .instruction()
+        .unwrap();
+
+    let result = ctx.execute_instruction(refund_ix, &[&depositor]).unwrap();
+
+    // This should fail with DecideWindowNotOver, before any proof is checked
+    result.assert_failure();
+
+    println!("✓ Refund correctly rejected while the decide window is still open");
+}
+
+#[test]
+fn test_deposit_rejected_after_mint_window_closes() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let decider = Keypair::new();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: Some(decider.pubkey()),
+            mint_term_end_slot: Some(10),
+            decide_term_end_slot: Some(20),
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    // Warp well past mint_term_end_slot
+    ctx.svm.warp_to_slot(1_000);
+
+    let deposit_amount = LAMPORTS_PER_SOL / 10;
+    let nullifier = random_bytes();
+    let secret = random_bytes();
+    let commitment = compute_commitment(&nullifier, &secret, deposit_amount);
+
+    let deposit_accounts = client::accounts::Deposit {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        depositor_token_account: None,
+        pool_token_vault: None,
+        depositor: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+    };
+
+    let deposit_args = client::args::Deposit {
+        commitment,
+        amount: deposit_amount,
+    };
+
+    let deposit_ix = ctx.program()
+        .accounts(deposit_accounts)
+        .args(deposit_args)
+        .instruction()
+        .unwrap();
+
+    let result = ctx.execute_instruction(deposit_ix, &[&authority]).unwrap();
+
+    // This should fail with MintWindowClosed
+    result.assert_failure();
+
+    println!("✓ Deposit correctly rejected once the mint window has closed");
+}
+
+/// Anchor's account discriminator: first 8 bytes of sha256("account:<Name>").
+fn account_discriminator(name: &str) -> [u8; 8] {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("account:{}", name).as_bytes());
+    let hash: [u8; 32] = hasher.finalize().into();
+    hash[..8].try_into().unwrap()
+}
+
+#[test]
+fn test_withdraw_rejects_replayed_nullifier() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let relayer = Keypair::new();
+    ctx.svm.airdrop(&relayer.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let recipient = Keypair::new();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: None,
+            mint_term_end_slot: None,
+            decide_term_end_slot: None,
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    // Plant an already-initialized nullifier_record at the PDA a withdrawal
+    // for this nullifier_hash would use, simulating one already spent.
+    let nullifier_hash = random_bytes();
+    let (nullifier_pda, _) =
+        Pubkey::find_program_address(&[b"nullifier", pool_pda.as_ref(), &nullifier_hash], &PROGRAM_ID);
+
+    let discriminator = account_discriminator("NullifierRecord");
+    ctx.svm
+        .set_account(
+            nullifier_pda,
+            Account {
+                lamports: 10 * LAMPORTS_PER_SOL,
+                data: discriminator.to_vec(),
+                owner: PROGRAM_ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        )
+        .unwrap();
+
+    let withdraw_accounts = client::accounts::Withdraw {
+        pool: pool_pda,
+        nullifier_record: nullifier_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        recipient_token_account: None,
+        pool_token_vault: None,
+        recipient: recipient.pubkey(),
+        relayer: relayer.pubkey(),
+        relayer_token_account: None,
+        verifier_program: SUNSPOT_VERIFIER_ID,
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+    };
+
+    let withdraw_args = client::args::Withdraw {
+        proof: vec![],
+        nullifier_hash,
+        root: [0u8; 32],
+        recipient: recipient.pubkey(),
+        amount: LAMPORTS_PER_SOL / 10,
+        relayer: relayer.pubkey(),
+        fee: 0,
+    };
+
+    let withdraw_ix = ctx.program()
+        .accounts(withdraw_accounts)
+        .args(withdraw_args)
+        .instruction()
+        .unwrap();
+
+    let result = ctx.execute_instruction(withdraw_ix, &[&relayer]).unwrap();
+
+    // `init` on nullifier_record fails because the PDA is already in use,
+    // rejecting the replay before any proof is ever checked.
+    result.assert_failure();
+
+    println!("✓ Withdraw correctly rejects a replayed nullifier_hash");
+}
+
+#[test]
+fn test_withdraw_rejects_relayer_mismatch() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let impostor = Keypair::new();
+    ctx.svm.airdrop(&impostor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let real_relayer = Keypair::new();
+    let recipient = Keypair::new();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: None,
+            mint_term_end_slot: None,
+            decide_term_end_slot: None,
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    let nullifier_hash = random_bytes();
+    let (nullifier_pda, _) =
+        Pubkey::find_program_address(&[b"nullifier", pool_pda.as_ref(), &nullifier_hash], &PROGRAM_ID);
+
+    // The proof was generated for `real_relayer`, but `impostor` submits and
+    // signs the transaction, trying to collect the fee themselves.
+    let withdraw_accounts = client::accounts::Withdraw {
+        pool: pool_pda,
+        nullifier_record: nullifier_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        recipient_token_account: None,
+        pool_token_vault: None,
+        recipient: recipient.pubkey(),
+        relayer: impostor.pubkey(),
+        relayer_token_account: None,
+        verifier_program: SUNSPOT_VERIFIER_ID,
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+    };
+
+    let withdraw_args = client::args::Withdraw {
+        proof: vec![],
+        nullifier_hash,
+        root: [0u8; 32],
+        recipient: recipient.pubkey(),
+        amount: LAMPORTS_PER_SOL / 10,
+        relayer: real_relayer.pubkey(),
+        fee: 1_000,
+    };
+
+    let withdraw_ix = ctx.program()
+        .accounts(withdraw_accounts)
+        .args(withdraw_args)
+        .instruction()
+        .unwrap();
+
+    let result = ctx.execute_instruction(withdraw_ix, &[&impostor]).unwrap();
+
+    // This should fail with RelayerMismatch, before any proof is checked
+    result.assert_failure();
+
+    println!("✓ Withdraw correctly rejects a relayer that doesn't match the proof's relayer");
+}
+
+#[test]
+fn test_withdraw_rejects_excessive_fee() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let relayer = Keypair::new();
+    ctx.svm.airdrop(&relayer.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let recipient = Keypair::new();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: None,
+            mint_term_end_slot: None,
+            decide_term_end_slot: None,
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    let nullifier_hash = random_bytes();
+    let (nullifier_pda, _) =
+        Pubkey::find_program_address(&[b"nullifier", pool_pda.as_ref(), &nullifier_hash], &PROGRAM_ID);
+
+    let amount = LAMPORTS_PER_SOL / 10;
+
+    let withdraw_accounts = client::accounts::Withdraw {
+        pool: pool_pda,
+        nullifier_record: nullifier_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        recipient_token_account: None,
+        pool_token_vault: None,
+        recipient: recipient.pubkey(),
+        relayer: relayer.pubkey(),
+        relayer_token_account: None,
+        verifier_program: SUNSPOT_VERIFIER_ID,
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+    };
+
+    let withdraw_args = client::args::Withdraw {
+        proof: vec![],
+        nullifier_hash,
+        root: [0u8; 32],
+        recipient: recipient.pubkey(),
+        amount,
+        relayer: relayer.pubkey(),
+        fee: amount, // fee must be strictly less than amount
+    };
+
+    let withdraw_ix = ctx.program()
+        .accounts(withdraw_accounts)
+        .args(withdraw_args)
+        .instruction()
+        .unwrap();
+
+    let result = ctx.execute_instruction(withdraw_ix, &[&relayer]).unwrap();
+
+    // This should fail with RelayerFeeTooHigh, before any proof is checked
+    result.assert_failure();
+
+    println!("✓ Withdraw correctly rejects a fee that isn't less than the withdrawal amount");
+}
+
+#[test]
+fn test_zeros_table_is_internally_consistent() {
+    // ZEROS[i] must be poseidon_hash(ZEROS[i-1], ZEROS[i-1]), and EMPTY_ROOT
+    // must be ZEROS[TREE_DEPTH - 1] hashed one level further, exactly as the
+    // doc comment on ZEROS claims. A bit flipped in any of these hardcoded
+    // arrays would otherwise only surface once real withdrawal proofs start
+    // failing against a root this program could never have produced.
+    for level in 1..TREE_DEPTH {
+        let expected = poseidon_hash(&ZEROS[level - 1], &ZEROS[level - 1]);
+        assert_eq!(
+            ZEROS[level], expected,
+            "ZEROS[{level}] does not equal poseidon_hash(ZEROS[{}], ZEROS[{}])",
+            level - 1,
+            level - 1
+        );
+    }
+
+    let expected_empty_root = poseidon_hash(&ZEROS[TREE_DEPTH - 1], &ZEROS[TREE_DEPTH - 1]);
+    assert_eq!(
+        EMPTY_ROOT, expected_empty_root,
+        "EMPTY_ROOT does not equal poseidon_hash(ZEROS[TREE_DEPTH - 1], ZEROS[TREE_DEPTH - 1])"
+    );
+
+    println!("✓ ZEROS/EMPTY_ROOT are internally consistent with the claimed derivation");
+}
+
+#[test]
+fn test_deposit_root_matches_independently_computed_tree() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: None,
+            mint_term_end_slot: None,
+            decide_term_end_slot: None,
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    // Three deposits exercise both the even-idx branch (leaf 0, leaf 2 --
+    // `filled_subtrees[0]` gets overwritten) and the odd-idx branch (leaf 1
+    // -- the previously filled subtree gets combined with the new leaf).
+    let mut leaves = Vec::new();
+    for i in 0..3u64 {
+        let deposit_amount = 50_000_000 + i * 10_000_000;
+        let nullifier = random_bytes();
+        let secret = random_bytes();
+        let commitment = to_field_element(compute_commitment(&nullifier, &secret, deposit_amount));
+        leaves.push(commitment);
+
+        let deposit_accounts = client::accounts::Deposit {
+            pool: pool_pda,
+            pool_vault: vault_pda,
+            mint: None,
+            depositor_token_account: None,
+            pool_token_vault: None,
+            depositor: authority.pubkey(),
+            system_program: SYSTEM_PROGRAM_ID,
+            token_program: None,
+        };
+
+        let deposit_ix = ctx.program()
+            .accounts(deposit_accounts)
+            .args(client::args::Deposit {
+                commitment,
+                amount: deposit_amount,
+            })
+            .instruction()
+            .unwrap();
+
+        ctx.execute_instruction(deposit_ix, &[&authority]).unwrap().assert_success();
+    }
+
+    let pool_state = fetch_pool(&ctx, &pool_pda);
+    let onchain_root = pool_state.roots[pool_state.current_root_index as usize];
+    let expected_root = expected_root_after_inserts(&leaves);
+
+    assert_eq!(
+        onchain_root, expected_root,
+        "on-chain root after 3 deposits does not match the independently computed tree"
+    );
+
+    println!("✓ On-chain root after 3 deposits matches an independently computed tree");
+}
+
+#[test]
+fn test_withdraw_root_check_stays_in_sync_with_deposit() {
+    // Set up LiteSVM with our program
+    let mut ctx = AnchorLiteSVM::build_with_program(
+        PROGRAM_ID,
+        include_bytes!("../../../target/deploy/private_transfers.so"),
+    );
+
+    let authority = Keypair::new();
+    ctx.svm.airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let (pool_pda, _) = find_pool_pda();
+    let (vault_pda, _) = find_vault_pda(&pool_pda);
+
+    let init_accounts = client::accounts::Initialize {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        pool_token_vault: None,
+        authority: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+        associated_token_program: None,
+    };
+
+    let init_ix = ctx.program()
+        .accounts(init_accounts)
+        .args(client::args::Initialize {
+            token_mint: Pubkey::default(),
+            decider: None,
+            mint_term_end_slot: None,
+            decide_term_end_slot: None,
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(init_ix, &[&authority]).unwrap().assert_success();
+
+    let deposit_amount = LAMPORTS_PER_SOL / 10;
+    let nullifier = random_bytes();
+    let secret = random_bytes();
+    let commitment = to_field_element(compute_commitment(&nullifier, &secret, deposit_amount));
+
+    let deposit_accounts = client::accounts::Deposit {
+        pool: pool_pda,
+        pool_vault: vault_pda,
+        mint: None,
+        depositor_token_account: None,
+        pool_token_vault: None,
+        depositor: authority.pubkey(),
+        system_program: SYSTEM_PROGRAM_ID,
+        token_program: None,
+    };
+
+    let deposit_ix = ctx.program()
+        .accounts(deposit_accounts)
+        .args(client::args::Deposit {
+            commitment,
+            amount: deposit_amount,
+        })
+        .instruction()
+        .unwrap();
+
+    ctx.execute_instruction(deposit_ix, &[&authority]).unwrap().assert_success();
+
+    // `withdraw` accepts exactly the roots `pool.is_known_root` recognizes,
+    // which is exactly what `insert_leaf` just wrote into `pool.roots` --
+    // this is the property real withdrawal proofs depend on, without needing
+    // a real proof to exercise it (the real Sunspot verifier isn't available
+    // in this harness; see mock_verifier's doc comment).
+    let pool_state = fetch_pool(&ctx, &pool_pda);
+    let deposit_root = pool_state.roots[pool_state.current_root_index as usize];
+
+    assert!(
+        pool_state.is_known_root(&deposit_root),
+        "the root insert_leaf just produced should be a root withdraw recognizes"
+    );
+    assert!(
+        !pool_state.is_known_root(&[0xAB; 32]),
+        "a root no deposit ever produced should not be recognized by withdraw"
+    );
+
+    println!("✓ The root deposit just wrote is exactly the root withdraw's is_known_root accepts");
+}